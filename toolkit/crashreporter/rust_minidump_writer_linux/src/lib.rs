@@ -8,9 +8,48 @@ use libc::pid_t;
 use minidump_writer::crash_context::CrashContext;
 use minidump_writer::minidump_writer::MinidumpWriter;
 use nsstring::nsCString;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::mem;
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_int};
+use std::os::unix::io::FromRawFd;
+
+// Opens (creating/truncating as needed) the minidump destination file,
+// formatting any failure the same way regardless of which out-param type
+// the caller ultimately reports it through.
+fn open_dump_file(path: &str) -> Result<std::fs::File, String> {
+    std::fs::OpenOptions::new()
+        .create(true) // Create file if it doesn't exist
+        .write(true)
+        .truncate(true) // Truncate file
+        .open(path)
+        .map_err(|x| {
+            format!(
+                "Wrapper error when opening minidump destination at {:?}: {:#}",
+                path,
+                anyhow::Error::new(x)
+            )
+        })
+}
+
+// Allocates an owned, NUL-terminated C string for `msg` and hands it back
+// through `error_msg`, for callers that can't depend on nsstring. The
+// returned pointer must be released with `free_minidump_error`.
+unsafe fn set_owned_error(error_msg: *mut *mut c_char, msg: &str) {
+    if error_msg.is_null() {
+        return;
+    }
+    let c_msg = CString::new(msg)
+        .unwrap_or_else(|_| CString::new("error message contained an embedded NUL byte").unwrap());
+    *error_msg = c_msg.into_raw();
+}
+
+// This function will be exposed to C++
+#[no_mangle]
+pub unsafe extern "C" fn free_minidump_error(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        drop(CString::from_raw(ptr));
+    }
+}
 
 // This structure will be exposed to C++
 #[repr(C)]
@@ -45,18 +84,10 @@ pub unsafe extern "C" fn write_minidump_linux(
         }
     };
 
-    let mut dump_file = match std::fs::OpenOptions::new()
-        .create(true) // Create file if it doesn't exist
-        .write(true) // Truncate file
-        .open(path)
-    {
+    let mut dump_file = match open_dump_file(path) {
         Ok(f) => f,
-        Err(x) => {
-            error_msg.assign(&format!(
-                "Wrapper error when opening minidump destination at {:?}: {:#}",
-                path,
-                anyhow::Error::new(x)
-            ));
+        Err(msg) => {
+            error_msg.assign(&msg);
             return false;
         }
     };
@@ -72,6 +103,52 @@ pub unsafe extern "C" fn write_minidump_linux(
     }
 }
 
+// This function will be exposed to C++
+//
+// Identical to `write_minidump_linux` except that it reports errors through
+// an owned, heap-allocated C string rather than `nsCString`, so that this
+// crate can be consumed by callers outside the XPCOM tree. The returned
+// error pointer, if non-null, must be released with `free_minidump_error`.
+#[no_mangle]
+pub unsafe extern "C" fn write_minidump_linux_raw(
+    dump_path: *const c_char,
+    child: pid_t,
+    child_blamed_thread: pid_t,
+    error_msg: *mut *mut c_char,
+) -> bool {
+    assert!(!dump_path.is_null());
+    let c_path = CStr::from_ptr(dump_path);
+    let path = match c_path.to_str() {
+        Ok(s) => s,
+        Err(x) => {
+            set_owned_error(
+                error_msg,
+                &format!(
+                    "Wrapper error. Path not convertable: {:#}",
+                    anyhow::Error::new(x)
+                ),
+            );
+            return false;
+        }
+    };
+
+    let mut dump_file = match open_dump_file(path) {
+        Ok(f) => f,
+        Err(msg) => {
+            set_owned_error(error_msg, &msg);
+            return false;
+        }
+    };
+
+    match MinidumpWriter::new(child, child_blamed_thread).dump(&mut dump_file) {
+        Ok(_) => true,
+        Err(x) => {
+            set_owned_error(error_msg, &format!("{:#}", anyhow::Error::new(x)));
+            false
+        }
+    }
+}
+
 // This function will be exposed to C++
 #[no_mangle]
 pub unsafe extern "C" fn write_minidump_linux_with_context(
@@ -96,18 +173,10 @@ pub unsafe extern "C" fn write_minidump_linux_with_context(
         }
     };
 
-    let mut dump_file = match std::fs::OpenOptions::new()
-        .create(true) // Create file if it doesn't exist
-        .write(true) // Truncate file
-        .open(path)
-    {
+    let mut dump_file = match open_dump_file(path) {
         Ok(f) => f,
-        Err(x) => {
-            error_msg.assign(&format!(
-                "Wrapper error when opening minidump destination at {:?}: {:#}",
-                path,
-                anyhow::Error::new(x)
-            ));
+        Err(msg) => {
+            error_msg.assign(&msg);
             return false;
         }
     };
@@ -125,3 +194,229 @@ pub unsafe extern "C" fn write_minidump_linux_with_context(
         }
     }
 }
+
+// This function will be exposed to C++
+//
+// Identical to `write_minidump_linux_with_context` except that it reports
+// errors through an owned, heap-allocated C string rather than `nsCString`,
+// so that this crate can be consumed by callers outside the XPCOM tree. The
+// returned error pointer, if non-null, must be released with
+// `free_minidump_error`.
+#[no_mangle]
+pub unsafe extern "C" fn write_minidump_linux_with_context_raw(
+    dump_path: *const c_char,
+    child: pid_t,
+    context: *const InternalCrashContext,
+    error_msg: *mut *mut c_char,
+) -> bool {
+    assert!(!dump_path.is_null());
+    let c_path = CStr::from_ptr(dump_path);
+
+    assert!(!context.is_null());
+    let cc: CrashContext = mem::transmute_copy(&(*(context as *const CrashContext)));
+    let path = match c_path.to_str() {
+        Ok(s) => s,
+        Err(x) => {
+            set_owned_error(
+                error_msg,
+                &format!(
+                    "Wrapper error. Path not convertable: {:#}",
+                    anyhow::Error::new(x)
+                ),
+            );
+            return false;
+        }
+    };
+
+    let mut dump_file = match open_dump_file(path) {
+        Ok(f) => f,
+        Err(msg) => {
+            set_owned_error(error_msg, &msg);
+            return false;
+        }
+    };
+
+    match MinidumpWriter::new(child, cc.inner.tid)
+        .set_crash_context(cc)
+        .dump(&mut dump_file)
+    {
+        Ok(_) => true,
+        Err(x) => {
+            set_owned_error(error_msg, &format!("{:#}", anyhow::Error::new(x)));
+            false
+        }
+    }
+}
+
+// This function will be exposed to C++
+//
+// Writes the minidump to an already-open file descriptor (e.g. a pipe or a
+// destination a crash-generation server pre-created) instead of opening a
+// path. `raw_fd` is borrowed, not taken: it is wrapped without closing it on
+// drop, so ownership stays with the caller.
+#[no_mangle]
+pub unsafe extern "C" fn write_minidump_linux_fd(
+    raw_fd: c_int,
+    child: pid_t,
+    child_blamed_thread: pid_t,
+    error_msg: &mut nsCString,
+) -> bool {
+    let mut dump_file = mem::ManuallyDrop::new(std::fs::File::from_raw_fd(raw_fd));
+
+    match MinidumpWriter::new(child, child_blamed_thread).dump(&mut *dump_file) {
+        Ok(_) => true,
+        Err(x) => {
+            error_msg.assign(&format!("{:#}", anyhow::Error::new(x)));
+            false
+        }
+    }
+}
+
+// This function will be exposed to C++
+//
+// Writes the minidump into an in-memory buffer instead of to disk, for
+// callers that want to hash or compress it before it ever touches a file.
+// On success, `out_ptr`/`out_len` describe a heap-allocated buffer that must
+// be released with `free_minidump_buffer`.
+#[no_mangle]
+pub unsafe extern "C" fn write_minidump_linux_to_buffer(
+    child: pid_t,
+    child_blamed_thread: pid_t,
+    error_msg: &mut nsCString,
+    out_ptr: *mut *mut u8,
+    out_len: *mut usize,
+) -> bool {
+    assert!(!out_ptr.is_null());
+    assert!(!out_len.is_null());
+
+    let mut cursor = std::io::Cursor::new(Vec::new());
+
+    match MinidumpWriter::new(child, child_blamed_thread).dump(&mut cursor) {
+        Ok(_) => {
+            // `into_boxed_slice` guarantees capacity == len (reallocating if
+            // needed), unlike `shrink_to_fit` followed by `Vec::from_raw_parts`
+            // with the post-shrink length, which isn't guaranteed to match the
+            // buffer's actual allocated capacity.
+            let boxed = cursor.into_inner().into_boxed_slice();
+            *out_len = boxed.len();
+            *out_ptr = Box::into_raw(boxed) as *mut u8;
+            true
+        }
+        Err(x) => {
+            error_msg.assign(&format!("{:#}", anyhow::Error::new(x)));
+            false
+        }
+    }
+}
+
+// This function will be exposed to C++
+#[no_mangle]
+pub unsafe extern "C" fn free_minidump_buffer(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(std::slice::from_raw_parts_mut(ptr, len)));
+    }
+}
+
+// Returns the paths of the files that belong to a given minidump: the `.dmp`
+// itself plus the sibling `.extra`/`.memory.json.gz` sharing its stem.
+// Existence is not checked: both sibling paths are always returned, even
+// though not every crash produces both an `.extra` and a `.memory.json.gz`
+// file, so callers must skip the ones that aren't on disk.
+fn minidump_siblings(dmp_path: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut siblings = vec![dmp_path.to_path_buf()];
+    let stem = dmp_path.with_extension("");
+    for suffix in [".extra", ".memory.json.gz"] {
+        let mut sibling = stem.clone().into_os_string();
+        sibling.push(suffix);
+        siblings.push(std::path::PathBuf::from(sibling));
+    }
+    siblings
+}
+
+// This function will be exposed to C++
+//
+// Prunes a minidump directory down to the `keep` most-recently-modified
+// `.dmp` files, removing every older one along with its `.extra`/
+// `.memory.json.gz` siblings. Subdirectories are skipped, entries whose
+// mtime can't be read are treated as the oldest (and thus pruned first),
+// and every removal failure is accumulated into `error_msg` rather than
+// aborting the whole call. The return value reflects whether the
+// retention target was actually met: it's `false` if any `.dmp` beyond
+// `keep` is still on disk afterward, even though sibling-only failures
+// (an `.extra`/`.memory.json.gz` that couldn't be removed) are still
+// reported in `error_msg` without affecting it.
+#[no_mangle]
+pub unsafe extern "C" fn prune_minidumps(
+    dir_path: *const c_char,
+    keep: usize,
+    error_msg: &mut nsCString,
+) -> bool {
+    assert!(!dir_path.is_null());
+    let c_path = CStr::from_ptr(dir_path);
+    let dir = match c_path.to_str() {
+        Ok(s) => s,
+        Err(x) => {
+            error_msg.assign(&format!(
+                "Wrapper error. Path not convertable: {:#}",
+                anyhow::Error::new(x)
+            ));
+            return false;
+        }
+    };
+
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(x) => {
+            error_msg.assign(&format!(
+                "Wrapper error when reading minidump directory at {:?}: {:#}",
+                dir,
+                anyhow::Error::new(x)
+            ));
+            return false;
+        }
+    };
+
+    let mut dumps: Vec<(std::path::PathBuf, std::time::SystemTime)> = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            continue;
+        }
+        if path.extension().and_then(|ext| ext.to_str()) != Some("dmp") {
+            continue;
+        }
+        let mtime = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .unwrap_or(std::time::SystemTime::UNIX_EPOCH);
+        dumps.push((path, mtime));
+    }
+
+    dumps.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut failures = String::new();
+    let mut retention_target_met = true;
+    for (dmp_path, _) in dumps.into_iter().skip(keep) {
+        for sibling in minidump_siblings(&dmp_path) {
+            if !sibling.exists() {
+                continue;
+            }
+            if let Err(x) = std::fs::remove_file(&sibling) {
+                failures.push_str(&format!(
+                    "failed to remove {:?}: {:#}\n",
+                    sibling,
+                    anyhow::Error::new(x)
+                ));
+                if sibling == dmp_path {
+                    retention_target_met = false;
+                }
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        error_msg.assign(&failures);
+    }
+
+    retention_target_met
+}